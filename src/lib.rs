@@ -1,9 +1,15 @@
 /*! An easier way to mask code blocks than commenting them out.
  *
- * Due to [rust#54727](https://github.com/rust-lang/rust/issues/54727), it can
- * not be used on `{ }` blocks yet.
+ * Due to [rust#54727](https://github.com/rust-lang/rust/issues/54727), it
+ * can not be attached directly to a bare `{ }` block expression on stable
+ * Rust: an attribute macro may only target a block expression under the
+ * unstable `stmt_expr_attributes` feature, so that restriction stands as
+ * long as this crate targets stable. It can, however, be attached to a
+ * `fn`, and it sees through `Delimiter::None` groups, so a block forwarded
+ * from a `macro_rules!` as a captured `$body:block` fragment is found and
+ * hollowed correctly.
  */
-use proc_macro::{Delimiter, Group, TokenStream, TokenTree};
+use proc_macro::{Delimiter, Group, Ident, Punct, Spacing, Span, TokenStream, TokenTree};
 
 /** Swallow the body of the `fn` it's attached to.
  *
@@ -16,6 +22,25 @@ use proc_macro::{Delimiter, Group, TokenStream, TokenTree};
  * function_to_swallow()
  * ```
  *
+ * It sees through a `Delimiter::None` group, so it still finds the body when
+ * a `macro_rules!` macro forwards it as a captured `$body:block` fragment
+ * rather than writing it out directly:
+ *
+ * ```rust
+ * macro_rules! generate_stub {
+ *     ($name:ident $body:block) => {
+ *         #[hollow::hollow]
+ *         fn $name() $body
+ *     };
+ * }
+ *
+ * generate_stub!(forwarded {
+ *     panic!("this panic! should be swallowed by hollow");
+ * });
+ *
+ * forwarded()
+ * ```
+ *
  * When its return type does not `impl Default`, or a value other than the
  * default is desired:
  *
@@ -35,25 +60,129 @@ use proc_macro::{Delimiter, Group, TokenStream, TokenTree};
  *
  * assert_eq!(42, custom_return().value);
  * ```
+ *
+ * A `when(...)` predicate can be given to only hollow the fn under some
+ * compile-time condition, keeping the real body everywhere else:
+ *
+ * ```rust
+ * fn do_the_real_thing() {}
+ *
+ * #[hollow::hollow(when(feature = "stub"))]
+ * fn maybe_stubbed() {
+ *     do_the_real_thing();
+ * }
+ * ```
+ *
+ * This expands to two copies of the fn, one `#[cfg(feature = "stub")]` with
+ * the body swallowed, and one `#[cfg(not(feature = "stub"))]` with the body
+ * left untouched.
+ *
+ * Parameters that become unused once the body is swallowed do not trigger
+ * `unused_variables`; a `let _ = (&param1, &param2, ...);` guard is
+ * inserted ahead of the stub value to keep every named parameter referenced.
+ * This holds even past a `pub(crate)`/`pub(super)` visibility modifier's own
+ * parens and a `Fn`/`FnMut`/`FnOnce`-bounded generic parameter, neither of
+ * which are mistaken for the fn's parameter list:
+ *
+ * ```rust
+ * #[hollow::hollow]
+ * pub(crate) fn higher_order<F: Fn(i32) -> i32>(callback: F, value: i32) -> i32 {
+ *     callback(value)
+ * }
+ *
+ * assert_eq!(0, higher_order(|x| x + 1, 5));
+ * ```
+ *
+ * It can also be attached to a whole `impl` or `trait` block, stubbing the
+ * body of every method inside in one go:
+ *
+ * ```rust
+ * trait Shape {
+ *     fn area(&self) -> f64;
+ *     fn name(&self) -> &'static str;
+ * }
+ *
+ * struct Square {
+ *     side: f64,
+ * }
+ *
+ * #[hollow::hollow(methods(area = 42.0))]
+ * impl Shape for Square {
+ *     fn area(&self) -> f64 {
+ *         self.side * self.side
+ *     }
+ *
+ *     fn name(&self) -> &'static str {
+ *         "square"
+ *     }
+ * }
+ *
+ * assert_eq!(42.0, Square { side: 3.0 }.area());
+ * ```
+ *
+ * Each method is walked through the same `fn` logic as a directly-attached
+ * `hollow`, so a `Fn`-bounded generic parameter is handled the same way:
+ *
+ * ```rust
+ * struct Adder;
+ *
+ * #[hollow::hollow]
+ * impl Adder {
+ *     fn combine<F: Fn(i32, i32) -> i32>(&self, op: F, a: i32, b: i32) -> i32 {
+ *         op(a, b)
+ *     }
+ * }
+ *
+ * assert_eq!(0, Adder.combine(|x, y| x + y, 2, 3));
+ * ```
+ *
+ * A fn-pointer-typed associated `const`/`static`, whose own brace-delimited
+ * initializer and leading `fn` (part of its type, not a method) would
+ * otherwise look like a hollow-able method, is left untouched:
+ *
+ * ```rust
+ * struct Calc;
+ *
+ * #[hollow::hollow]
+ * impl Calc {
+ *     const ADD: fn(i32, i32) -> i32 = { |a, b| a + b };
+ * }
+ *
+ * assert_eq!(7, (Calc::ADD)(3, 4));
+ * ```
+ *
+ * A doc comment or an attribute such as `#[inline]` above a method does not
+ * stop it from being recognized and hollowed:
+ *
+ * ```rust
+ * struct Doc;
+ *
+ * #[hollow::hollow]
+ * impl Doc {
+ *     /// Would panic if its body weren't swallowed.
+ *     #[inline]
+ *     fn documented(&self) -> i32 {
+ *         panic!("this panic! should be swallowed by hollow");
+ *     }
+ * }
+ *
+ * assert_eq!(0, Doc.documented());
+ * ```
+ *
+ * Non-`fn` items (associated consts, types, `use`) and bodyless trait method
+ * signatures are preserved verbatim. `methods(name = expr, ...)` supplies a
+ * per-method override keyed by method name; methods without an entry fall
+ * back to `Default::default()`.
  */
 #[proc_macro_attribute]
 pub fn hollow(attr: TokenStream, item: TokenStream) -> TokenStream {
-	let body_tokens = if attr.is_empty() {
-		"Default::default()".parse().unwrap()
-	} else {
-		let mut iter = attr.into_iter();
-		let Some(TokenTree::Ident(next)) = iter.next() else {
-			panic!("invalid attr argument");
-		};
-		assert_eq!("value", &next.to_string());
-		let Some(TokenTree::Punct(next)) = iter.next() else {
-			panic!("invalid attr argument");
-		};
-		assert_eq!('=', next.as_char());
-		TokenStream::from_iter(iter)
-	};
+	let (prefix, body) = split_body(item);
 
-	let mut tokens = Vec::new();
+	if let ItemKind::ImplOrTrait = item_kind(&prefix) {
+		let methods = parse_methods_attr(attr);
+		let body = body.expect("hollowed impl/trait block has no body");
+		return hollow_block(prefix, body, &methods);
+	}
 
 	/* Items to be hollowed are basically fns; they start with a few Idents,
 	 * optionally a <>-delimited generics Group, then a ()- delimited
@@ -65,17 +194,512 @@ pub fn hollow(attr: TokenStream, item: TokenStream) -> TokenStream {
 	 * For fns with a return type, if no attr argument is given, we insert a
 	 * `Default::default()` as the body; otherwise, insert the attr argument.
 	 */
-	for token in item.into_iter() {
+	// A `when(...)` predicate given without a `value = ...` still gets
+	// `Default::default()` as the body.
+	let (when_pred, body_tokens) = parse_attr(attr);
+	let body_tokens = prepend_unused_guard(&prefix, body_tokens);
+
+	let stub = TokenTree::Group(Group::new(Delimiter::Brace, body_tokens));
+
+	match when_pred {
+		None => TokenStream::from_iter(prefix.into_iter().chain([stub])),
+		Some(when_pred) => {
+			let real = body.expect("hollowed item has no body to preserve");
+
+			let mut tokens = Vec::new();
+			tokens.extend(cfg_attr(&when_pred, false));
+			tokens.extend(prefix.iter().cloned());
+			tokens.push(stub);
+			tokens.extend(cfg_attr(&when_pred, true));
+			tokens.extend(prefix);
+			tokens.push(TokenTree::Group(real));
+
+			TokenStream::from_iter(tokens)
+		}
+	}
+}
+
+/** Whether an item's preserved prefix belongs to a `fn` or to an `impl`/
+ * `trait` block, decided by whichever of those keywords occurs first.
+ */
+enum ItemKind {
+	Fn,
+	ImplOrTrait,
+}
+
+fn item_kind(prefix: &[TokenTree]) -> ItemKind {
+	for token in prefix {
+		if let TokenTree::Ident(ident) = token {
+			match ident.to_string().as_str() {
+				"impl" | "trait" => return ItemKind::ImplOrTrait,
+				"fn" => return ItemKind::Fn,
+				_ => {}
+			}
+		}
+	}
+
+	ItemKind::Fn
+}
+
+/** Hollow every method inside an `impl`/`trait` block's body, leaving
+ * everything else (signature, non-fn items, bodyless trait methods)
+ * untouched.
+ */
+fn hollow_block(prefix: Vec<TokenTree>, body: Group, methods: &[(String, TokenStream)]) -> TokenStream {
+	let items = split_items(body.stream())
+		.into_iter()
+		.flat_map(|item| hollow_block_item(item, methods));
+
+	let mut tokens = prefix;
+	tokens.push(TokenTree::Group(Group::new(
+		Delimiter::Brace,
+		TokenStream::from_iter(items),
+	)));
+
+	TokenStream::from_iter(tokens)
+}
+
+/** Split a block body's tokens into a sequence of items, each ending at its
+ * terminating top-level `;` or `{}` body Group.
+ */
+fn split_items(tokens: TokenStream) -> Vec<Vec<TokenTree>> {
+	let mut items = Vec::new();
+	let mut current = Vec::new();
+
+	for token in tokens {
+		let terminates = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == ';')
+			|| matches!(&token, TokenTree::Group(group) if group.delimiter() == Delimiter::Brace);
+		current.push(token);
+		if terminates {
+			items.push(std::mem::take(&mut current));
+		}
+	}
+	if !current.is_empty() {
+		items.push(current);
+	}
+
+	items
+}
+
+/** Hollow a single item from inside an `impl`/`trait` block, if it's a `fn`
+ * with a body; everything else (non-fn items, bodyless trait signatures) is
+ * returned untouched.
+ */
+fn hollow_block_item(item: Vec<TokenTree>, methods: &[(String, TokenStream)]) -> Vec<TokenTree> {
+	let has_body = matches!(item.last(), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace);
+	let is_fn = item_starts_with_fn(&item);
+
+	if !has_body || !is_fn {
+		return item;
+	}
+
+	let (prefix, _body) = split_body(TokenStream::from_iter(item));
+
+	let body_tokens = method_name(&prefix)
+		.and_then(|name| methods.iter().find(|(candidate, _)| *candidate == name))
+		.map(|(_, expr)| expr.clone())
+		.unwrap_or_else(default_stub);
+	let body_tokens = prepend_unused_guard(&prefix, body_tokens);
+
+	let mut tokens = prefix;
+	tokens.push(TokenTree::Group(Group::new(Delimiter::Brace, body_tokens)));
+
+	tokens
+}
+
+/** Whether `item`'s own leading keyword, after any leading `#[attr]`/`#![attr]`
+ * attributes, an optional `pub(...)` visibility modifier, and any of
+ * `default`/`async`/`unsafe`/`const`/`extern "..."`, is `fn`.
+ *
+ * Anchoring on the item's own leading keyword, the same way `find_params`
+ * anchors its search after `fn` and the method's name, keeps a fn-pointer-
+ * typed associated `const`/`static` (e.g. `const F: fn() -> i32 = { .. };`)
+ * from being mistaken for a method just because `fn` and a brace body occur
+ * somewhere in it. Leading attributes (doc comments desugar to
+ * `#[doc = "..."]`) are skipped first so a documented or `#[inline]`d method
+ * is not mistaken for a non-`fn` item and left unhollowed.
+ */
+fn item_starts_with_fn(item: &[TokenTree]) -> bool {
+	let mut iter = item.iter().peekable();
+
+	loop {
+		let Some(TokenTree::Punct(punct)) = iter.peek() else { break };
+		if punct.as_char() != '#' {
+			break;
+		}
+		iter.next();
+
+		if matches!(iter.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == '!') {
+			iter.next();
+		}
+
+		if matches!(iter.peek(), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Bracket) {
+			iter.next();
+		}
+	}
+
+	if matches!(iter.peek(), Some(TokenTree::Ident(ident)) if ident.to_string() == "pub") {
+		iter.next();
+		if matches!(iter.peek(), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis) {
+			iter.next();
+		}
+	}
+
+	loop {
+		match iter.peek() {
+			Some(TokenTree::Ident(ident)) if matches!(ident.to_string().as_str(), "default" | "async" | "unsafe" | "const") => {
+				iter.next();
+			}
+			Some(TokenTree::Ident(ident)) if ident.to_string() == "extern" => {
+				iter.next();
+				if matches!(iter.peek(), Some(TokenTree::Literal(_))) {
+					iter.next();
+				}
+			}
+			_ => break,
+		}
+	}
+
+	matches!(iter.peek(), Some(TokenTree::Ident(ident)) if ident.to_string() == "fn")
+}
+
+/** Find the name of the method whose preserved prefix is `prefix`, i.e. the
+ * Ident right after `fn`.
+ */
+fn method_name(prefix: &[TokenTree]) -> Option<String> {
+	let mut iter = prefix.iter();
+
+	while let Some(token) = iter.next() {
+		if matches!(token, TokenTree::Ident(ident) if ident.to_string() == "fn") {
+			if let Some(TokenTree::Ident(name)) = iter.next() {
+				return Some(name.to_string());
+			}
+		}
+	}
+
+	None
+}
+
+/** Parse the `attr` argument of a `hollow`ed `impl`/`trait` block into its
+ * `methods(name = expr, ...)` overrides.
+ */
+fn parse_methods_attr(attr: TokenStream) -> Vec<(String, TokenStream)> {
+	if attr.is_empty() {
+		return Vec::new();
+	}
+
+	let mut iter = attr.into_iter();
+	let Some(TokenTree::Ident(next)) = iter.next() else {
+		panic!("invalid attr argument");
+	};
+	assert_eq!("methods", &next.to_string());
+	let Some(TokenTree::Group(group)) = iter.next() else {
+		panic!("invalid attr argument");
+	};
+	assert_eq!(Delimiter::Parenthesis, group.delimiter());
+
+	split_on_commas(group.stream())
+		.into_iter()
+		.map(|chunk| {
+			let mut iter = chunk.into_iter();
+			let Some(TokenTree::Ident(name)) = iter.next() else {
+				panic!("invalid method override");
+			};
+			let Some(TokenTree::Punct(eq)) = iter.next() else {
+				panic!("invalid method override");
+			};
+			assert_eq!('=', eq.as_char());
+
+			(name.to_string(), TokenStream::from_iter(iter))
+		})
+		.collect()
+}
+
+/** Walk `tokens` up to the brace-delimited body Group, returning everything
+ * before it verbatim and the body Group itself, if one is found.
+ *
+ * `Delimiter::None` groups are transparent to this walk: such a group
+ * arises when the decorated item is forwarded through a `macro_rules!`
+ * fragment (e.g. a captured `$body:block`), hiding the real body one level
+ * deeper. When one is found, its contents are searched the same way; the
+ * portion of it preceding the body, if any, is re-wrapped in an equivalent
+ * `Delimiter::None` group so spacing and hygiene are retained.
+ */
+fn split_body(tokens: TokenStream) -> (Vec<TokenTree>, Option<Group>) {
+	let mut prefix = Vec::new();
+
+	for token in tokens.into_iter() {
 		match token {
 			TokenTree::Group(group) => match group.delimiter() {
-				Delimiter::Brace => break,
-				_ => tokens.push(TokenTree::Group(group)),
+				Delimiter::Brace => return (prefix, Some(group)),
+				Delimiter::None => {
+					let (inner_prefix, body) = split_body(group.stream());
+					if !inner_prefix.is_empty() {
+						prefix.push(TokenTree::Group(Group::new(
+							Delimiter::None,
+							TokenStream::from_iter(inner_prefix),
+						)));
+					}
+					if body.is_some() {
+						return (prefix, body);
+					}
+				}
+				_ => prefix.push(TokenTree::Group(group)),
 			},
-			other => tokens.push(other),
+			other => prefix.push(other),
 		}
 	}
 
-	tokens.push(TokenTree::Group(Group::new(Delimiter::Brace, body_tokens)));
+	(prefix, None)
+}
 
-	TokenStream::from_iter(tokens)
+/** Prepend a `let _ = (&param1, &param2, ...);` guard, naming every fn
+ * parameter found in `prefix`, ahead of `body_tokens`, so that swallowing
+ * the real body does not leave every parameter unused. Parameters that
+ * can't trivially be named (`self`, `_`, destructuring patterns) are left
+ * out; if none can be named, `body_tokens` is returned untouched.
+ */
+fn prepend_unused_guard(prefix: &[TokenTree], body_tokens: TokenStream) -> TokenStream {
+	let idents = match find_params(prefix) {
+		Some(params) => param_idents(params),
+		None => Vec::new(),
+	};
+
+	if idents.is_empty() {
+		return body_tokens;
+	}
+
+	let refs = idents.into_iter().flat_map(|ident| {
+		[
+			TokenTree::Punct(Punct::new('&', Spacing::Alone)),
+			TokenTree::Ident(ident),
+			TokenTree::Punct(Punct::new(',', Spacing::Alone)),
+		]
+	});
+	let tuple = Group::new(Delimiter::Parenthesis, TokenStream::from_iter(refs));
+
+	let guard = [
+		TokenTree::Ident(Ident::new("let", Span::call_site())),
+		TokenTree::Ident(Ident::new("_", Span::call_site())),
+		TokenTree::Punct(Punct::new('=', Spacing::Alone)),
+		TokenTree::Group(tuple),
+		TokenTree::Punct(Punct::new(';', Spacing::Alone)),
+	];
+
+	TokenStream::from_iter(guard.into_iter().chain(body_tokens))
+}
+
+/** Find the fn's own `()`-delimited parameter Group among its preserved
+ * prefix tokens, if any.
+ *
+ * The search starts only after the `fn` keyword and its name, so a leading
+ * visibility modifier's parens (`pub(crate)`, `pub(super)`, `pub(in path)`)
+ * are never mistaken for the parameter list. From there it tracks
+ * `<...>` generic nesting the same way `split_on_commas` does, so a
+ * `Fn(...)`/`FnMut(...)`/`FnOnce(...)` trait-bound group inside the
+ * generics list is skipped rather than picked up as the parameter list.
+ */
+fn find_params(prefix: &[TokenTree]) -> Option<&Group> {
+	let mut iter = prefix.iter();
+	iter.by_ref().find(|token| matches!(token, TokenTree::Ident(ident) if ident.to_string() == "fn"))?;
+	iter.next()?; // the fn's name
+
+	let mut iter = iter.peekable();
+	let mut angle_depth = 0i32;
+	while let Some(token) = iter.next() {
+		if let TokenTree::Punct(punct) = token {
+			match punct.as_char() {
+				// The `->` of a `Fn(...) -> Ret` bound is two Puncts, not a
+				// closing `>`; swallow it whole so it doesn't decrement
+				// `angle_depth` early.
+				'-' if matches!(iter.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '>') => {
+					iter.next();
+				}
+				'<' => angle_depth += 1,
+				'>' => angle_depth = (angle_depth - 1).max(0),
+				_ => {}
+			}
+			continue;
+		}
+		if angle_depth == 0 {
+			if let TokenTree::Group(group) = token {
+				if group.delimiter() == Delimiter::Parenthesis {
+					return Some(group);
+				}
+			}
+		}
+	}
+
+	None
+}
+
+/** Extract the leading binding identifier out of each comma-separated
+ * parameter in `params`, skipping `self`, `_`, and patterns (destructuring,
+ * struct/tuple-struct patterns) that can't trivially be named.
+ */
+fn param_idents(params: &Group) -> Vec<Ident> {
+	split_on_commas(params.stream())
+		.into_iter()
+		.filter_map(param_ident)
+		.collect()
+}
+
+/** Split a token stream on its top-level commas (commas nested inside a
+ * `<...>` generic argument list are not split on).
+ */
+fn split_on_commas(tokens: TokenStream) -> Vec<TokenStream> {
+	let mut chunks = Vec::new();
+	let mut current = Vec::new();
+	let mut angle_depth = 0i32;
+	let mut tokens = tokens.into_iter().peekable();
+
+	while let Some(token) = tokens.next() {
+		if let TokenTree::Punct(punct) = &token {
+			match punct.as_char() {
+				// As in `find_params`, the `->` of a `Fn(...) -> Ret` bound is
+				// not a closing `>`; keep it whole so it doesn't decrement
+				// `angle_depth` early.
+				'-' if matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '>') => {
+					current.push(token);
+					current.extend(tokens.next());
+					continue;
+				}
+				'<' => angle_depth += 1,
+				'>' => angle_depth = (angle_depth - 1).max(0),
+				',' if angle_depth == 0 => {
+					chunks.push(TokenStream::from_iter(std::mem::take(&mut current)));
+					continue;
+				}
+				_ => {}
+			}
+		}
+		current.push(token);
+	}
+	if !current.is_empty() {
+		chunks.push(TokenStream::from_iter(current));
+	}
+
+	chunks
+}
+
+/** Pull the binding identifier, with its original span, out of a single
+ * `pat: type` parameter's tokens. Returns `None` for `self`, `_`, and
+ * patterns that aren't a plain (possibly `&`/`mut`/`ref`-qualified) ident.
+ */
+fn param_ident(chunk: TokenStream) -> Option<Ident> {
+	let mut iter = chunk.into_iter().peekable();
+
+	while matches!(iter.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == '&') {
+		iter.next();
+		if matches!(iter.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == '\'') {
+			iter.next();
+			iter.next();
+		}
+	}
+	while matches!(iter.peek(), Some(TokenTree::Ident(ident)) if matches!(ident.to_string().as_str(), "mut" | "ref"))
+	{
+		iter.next();
+	}
+
+	let Some(TokenTree::Ident(ident)) = iter.next() else {
+		return None;
+	};
+
+	if matches!(ident.to_string().as_str(), "self" | "_") {
+		return None;
+	}
+
+	// A Group directly following means this was a struct/tuple-struct
+	// pattern (e.g. `Foo { x }: Foo`), and a `::` means a path-qualified one
+	// (e.g. `a::Foo { x }: a::Foo`); neither is a plain binding.
+	match iter.next() {
+		Some(TokenTree::Group(_)) => return None,
+		Some(TokenTree::Punct(punct))
+			if punct.as_char() == ':'
+				&& punct.spacing() == Spacing::Joint
+				&& matches!(iter.peek(), Some(TokenTree::Punct(next)) if next.as_char() == ':') =>
+		{
+			return None;
+		}
+		_ => {}
+	}
+
+	Some(ident)
+}
+
+/** Parse the `attr` argument into an optional `when(...)` predicate and the
+ * tokens to use as the stub body.
+ */
+fn parse_attr(attr: TokenStream) -> (Option<TokenStream>, TokenStream) {
+	if attr.is_empty() {
+		return (None, default_stub());
+	}
+
+	let mut iter = attr.into_iter().peekable();
+
+	let when_pred = match iter.peek() {
+		Some(TokenTree::Ident(ident)) if ident.to_string() == "when" => {
+			iter.next();
+			let Some(TokenTree::Group(group)) = iter.next() else {
+				panic!("invalid attr argument");
+			};
+			assert_eq!(Delimiter::Parenthesis, group.delimiter());
+
+			if matches!(iter.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == ',') {
+				iter.next();
+			}
+
+			Some(group.stream())
+		}
+		_ => None,
+	};
+
+	let body_tokens = if iter.peek().is_none() {
+		default_stub()
+	} else {
+		let Some(TokenTree::Ident(next)) = iter.next() else {
+			panic!("invalid attr argument");
+		};
+		assert_eq!("value", &next.to_string());
+		let Some(TokenTree::Punct(next)) = iter.next() else {
+			panic!("invalid attr argument");
+		};
+		assert_eq!('=', next.as_char());
+		TokenStream::from_iter(iter)
+	};
+
+	(when_pred, body_tokens)
+}
+
+/** The default stub body used whenever no custom value is given: a call to
+ * `Default::default()`.
+ */
+fn default_stub() -> TokenStream {
+	"Default::default()".parse().unwrap()
+}
+
+/** Build a `#[cfg(<pred>)]`, or `#[cfg(not(<pred>))]` when `negate` is set,
+ * as a standalone attribute `TokenStream` to prepend to an item.
+ */
+fn cfg_attr(pred: &TokenStream, negate: bool) -> TokenStream {
+	let inner = if negate {
+		let not_group = Group::new(Delimiter::Parenthesis, pred.clone());
+		TokenStream::from_iter([
+			TokenTree::Ident(Ident::new("not", Span::call_site())),
+			TokenTree::Group(not_group),
+		])
+	} else {
+		pred.clone()
+	};
+
+	let cfg_group = Group::new(Delimiter::Parenthesis, inner);
+	let cfg_attr = TokenStream::from_iter([
+		TokenTree::Ident(Ident::new("cfg", Span::call_site())),
+		TokenTree::Group(cfg_group),
+	]);
+
+	TokenStream::from_iter([
+		TokenTree::Punct(Punct::new('#', Spacing::Alone)),
+		TokenTree::Group(Group::new(Delimiter::Bracket, cfg_attr)),
+	])
 }